@@ -3,15 +3,35 @@
 use std::fmt;
 use std::fmt::Display;
 use std::error::Error;
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::str::FromStr;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 const EUI48_LEN: usize = 6;
 type Eui48 = [u8; EUI48_LEN];
 
+/// Length in bytes of an IEEE EUI-64 address, used by e.g. InfiniBand and
+/// some modern NICs
+const EUI64_LEN: usize = 8;
+type Eui64 = [u8; EUI64_LEN];
+
+/// Length in bytes of a 4-byte SecureOn password
+const PASSWORD4_LEN: usize = 4;
+/// Length in bytes of a 6-byte SecureOn password
+const PASSWORD6_LEN: usize = 6;
+
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
 pub enum ParseError {
-    /// Format is incorrect
-    BadFormat,
-    /// Length is incorrect. Should be either 12, 14 or 17.
+    /// Format is incorrect. The index points at the offending group or
+    /// character, e.g. where a separator is missing, mismatched, or a
+    /// group has the wrong width.
+    BadFormat(usize),
+    /// Length is incorrect. For an EUI-48 MAC address this should be 12,
+    /// 14 or 17, for an EUI-64 address 16 or 23, for a SecureOn password
+    /// 8, 11, 12, 14 or 17.
     BadLength(usize),
     /// Character is not a valid hex character or one of -, : or .
     BadCharacter(char, usize),
@@ -20,7 +40,7 @@ pub enum ParseError {
 impl Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            ParseError::BadFormat => write!(f, "bad format"),
+            ParseError::BadFormat(idx) => write!(f, "bad format at index {}", idx),
             ParseError::BadLength(size) => write!(f, "bad length of {}", size),
             ParseError::BadCharacter(c, size) => {
                 write!(f, "bad character '{}' at index {}", c, size)
@@ -35,34 +55,31 @@ impl Error for ParseError {
     }
 }
 
-fn parse_mac(mac: &str) -> Result<Eui48, ParseError> {
-    let mut eui: Eui48 = [0; EUI48_LEN];
+// Parses a hex string into `out`, ignoring any -, : or . separators
+// wherever they appear. Used for both MAC addresses and SecureOn
+// passwords, which share the same nibble-pair format.
+fn parse_hex_bytes(hex: &str, out: &mut [u8]) -> Result<(), ParseError> {
     // whether the last nibble was the high_nibble
     let mut high_nibble = false;
-    // offset in the eui array
+    // offset in the out array
     let mut offset = 0;
 
-    match mac.len() {
-        12 | 14 | 17 => {}
-        _ => return Err(ParseError::BadLength(mac.len())),
-    };
-
-    for (idx, c) in mac.chars().enumerate() {
-        if offset >= EUI48_LEN {
-            return Err(ParseError::BadFormat);
+    for (idx, c) in hex.chars().enumerate() {
+        if offset >= out.len() {
+            return Err(ParseError::BadFormat(idx));
         }
         match c {
             '0'...'9' | 'a'...'f' | 'A'...'F' => {
                 match high_nibble {
                     false => {
                         high_nibble = true;
-                        eui[offset] = (c.to_digit(16).unwrap() as u8) << 4;
+                        out[offset] = (c.to_digit(16).unwrap() as u8) << 4;
                     }
                     true => {
                         high_nibble = false;
-                        eui[offset] += c.to_digit(16).unwrap() as u8;
+                        out[offset] += c.to_digit(16).unwrap() as u8;
                         // 1 "hex byte" (two chars, e.g. AA) parsed
-                        // increase target offset in eui
+                        // increase target offset in out
                         offset += 1;
                     }
                 }
@@ -72,9 +89,221 @@ fn parse_mac(mac: &str) -> Result<Eui48, ParseError> {
         }
     }
 
+    // an odd number of hex digits, or fewer than `out.len()` bytes worth,
+    // means the input didn't actually carry enough hex to fill `out` even
+    // though its overall length matched
+    if high_nibble || offset != out.len() {
+        return Err(ParseError::BadFormat(hex.len()));
+    }
+
+    Ok(())
+}
+
+// Checks that `str_len` (the textual length of a parsed address) is a
+// valid rendering of an address that is `addr_len` bytes long, i.e. bare
+// hex or hex grouped by `:`, `-` or `.`.
+fn valid_addr_str_len(addr_len: usize, str_len: usize) -> bool {
+    match addr_len {
+        EUI48_LEN => match str_len {
+            12 | 14 | 17 => true,
+            _ => false,
+        },
+        EUI64_LEN => match str_len {
+            16 | 23 => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+// Parses a MAC address of any supported length (EUI-48 or EUI-64) into a
+// `Vec` of `addr_len` bytes.
+fn parse_mac_generic(mac: &str, addr_len: usize) -> Result<Vec<u8>, ParseError> {
+    if !valid_addr_str_len(addr_len, mac.len()) {
+        return Err(ParseError::BadLength(mac.len()));
+    }
+
+    let mut addr = vec![0u8; addr_len];
+    parse_hex_bytes(mac, &mut addr)?;
+
+    Ok(addr)
+}
+
+fn parse_mac(mac: &str) -> Result<Eui48, ParseError> {
+    let addr = parse_mac_generic(mac, EUI48_LEN)?;
+    let mut eui: Eui48 = [0; EUI48_LEN];
+    eui.copy_from_slice(&addr);
     Ok(eui)
 }
 
+fn parse_eui64(mac: &str) -> Result<Eui64, ParseError> {
+    let addr = parse_mac_generic(mac, EUI64_LEN)?;
+    let mut eui: Eui64 = [0; EUI64_LEN];
+    eui.copy_from_slice(&addr);
+    Ok(eui)
+}
+
+// Parses a 4- or 6-byte SecureOn password, accepted in the same formats
+// as a MAC address.
+fn parse_password(password: &str) -> Result<Vec<u8>, ParseError> {
+    let len = match password.len() {
+        8 | 11 => PASSWORD4_LEN,
+        12 | 14 | 17 => PASSWORD6_LEN,
+        _ => return Err(ParseError::BadLength(password.len())),
+    };
+
+    let mut pass = vec![0u8; len];
+    parse_hex_bytes(password, &mut pass)?;
+
+    Ok(pass)
+}
+
+// Parses a MAC address the same way `parse_mac` does, but additionally
+// requires a single consistent separator and correctly sized groups:
+// `:`/`-` separated 2-char groups, `.` separated 4-char groups (Cisco
+// dotted triplets), or bare 12-char hex with no separator at all.
+fn parse_mac_strict(mac: &str) -> Result<Eui48, ParseError> {
+    let separator = mac.chars().find(|&c| c == ':' || c == '-' || c == '.');
+    let group_width = match separator {
+        None => 2,
+        Some(':') | Some('-') => 2,
+        Some('.') => 4,
+        Some(_) => unreachable!(),
+    };
+
+    let mut eui: Eui48 = [0; EUI48_LEN];
+    let mut offset = 0;
+    let mut group_start = 0;
+
+    for (idx, c) in mac.chars().enumerate() {
+        if let Some(sep) = separator {
+            if c == sep {
+                if idx - group_start != group_width {
+                    return Err(ParseError::BadFormat(group_start));
+                }
+                group_start = idx + 1;
+                continue;
+            }
+
+            if c == ':' || c == '-' || c == '.' {
+                return Err(ParseError::BadFormat(idx));
+            }
+        }
+
+        if !c.is_ascii_hexdigit() {
+            return Err(ParseError::BadCharacter(c, idx));
+        }
+
+        if offset >= EUI48_LEN {
+            return Err(ParseError::BadFormat(idx));
+        }
+
+        let nibble = c.to_digit(16).unwrap() as u8;
+        if (idx - group_start) % 2 == 0 {
+            eui[offset] = nibble << 4;
+        } else {
+            eui[offset] += nibble;
+            offset += 1;
+        }
+    }
+
+    // with no separator the whole string is a single group, so there's no
+    // fixed group_width to check it against
+    if separator.is_some() && mac.len() - group_start != group_width {
+        return Err(ParseError::BadFormat(group_start));
+    }
+
+    if offset != EUI48_LEN {
+        return Err(ParseError::BadFormat(mac.len()));
+    }
+
+    Ok(eui)
+}
+
+/// A parsed IEEE EUI-48 MAC address
+///
+/// Unlike the free functions in this crate, a `MacAddr` holds on to the
+/// parsed bytes so it can be stored, compared, hashed, and rendered back
+/// out, instead of only ever producing an opaque magic packet.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct MacAddr(Eui48);
+
+impl MacAddr {
+    /// Creates a `MacAddr` from raw bytes
+    pub fn new(octets: [u8; EUI48_LEN]) -> MacAddr {
+        MacAddr(octets)
+    }
+
+    /// Returns the 6 raw bytes of the address
+    pub fn octets(&self) -> [u8; EUI48_LEN] {
+        self.0
+    }
+
+    /// Builds the magic packet for this address
+    pub fn to_magic_packet(&self) -> [u8; 102] {
+        let mut packet = [0xFFu8; 102];
+
+        for i in 1..17 {
+            for j in 0..EUI48_LEN {
+                packet[i * EUI48_LEN + j] = self.0[j];
+            }
+        }
+
+        packet
+    }
+
+    /// Parses `mac`, rejecting mixed or ambiguous separators
+    ///
+    /// Unlike [`FromStr`](#impl-FromStr-for-MacAddr), which accepts `-`,
+    /// `:` and `.` mixed freely at any position, this requires a single
+    /// consistent separator with correctly sized groups: `aa:bb:cc:dd:ee:ff`
+    /// or `aa-bb-cc-dd-ee-ff` (2-char groups), the Cisco-style dotted
+    /// triplet `aabb.ccdd.eeff` (4-char groups), or bare `aabbccddeeff`
+    /// hex with no separator.
+    pub fn parse_strict(mac: &str) -> Result<MacAddr, ParseError> {
+        parse_mac_strict(mac).map(MacAddr)
+    }
+}
+
+impl FromStr for MacAddr {
+    type Err = ParseError;
+
+    fn from_str(mac: &str) -> Result<MacAddr, ParseError> {
+        parse_mac(mac).map(MacAddr)
+    }
+}
+
+impl Display for MacAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            self.0[0], self.0[1], self.0[2], self.0[3], self.0[4], self.0[5]
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Serialize for MacAddr {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> Deserialize<'de> for MacAddr {
+    fn deserialize<D>(deserializer: D) -> Result<MacAddr, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        MacAddr::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
 /// Creates a magic packet byte array for the given MAC address
 ///
 /// Accepted formats are the following:
@@ -87,23 +316,143 @@ fn parse_mac(mac: &str) -> Result<Eui48, ParseError> {
 ///
 /// aabbccddeeff
 pub fn create_magic_packet(mac: &str) -> Result<[u8; 102], ParseError> {
-    let mut packet = [0xFFu8; 102];
+    let mac: MacAddr = mac.parse()?;
+    Ok(mac.to_magic_packet())
+}
 
-    // parse MAC
-    let mac = parse_mac(mac)?;
+/// Creates a magic packet for the given MAC address, optionally appending
+/// a SecureOn password
+///
+/// Some NICs require a 4- or 6-byte SecureOn password, which is appended
+/// directly after the 16 MAC repetitions, making the returned packet 106
+/// or 108 bytes long. The password is parsed in the same formats as a
+/// MAC address (see [`create_magic_packet`]).
+pub fn create_magic_packet_with_password(
+    mac: &str,
+    password: Option<&str>,
+) -> Result<Vec<u8>, ParseError> {
+    let mut packet = create_magic_packet(mac)?.to_vec();
 
-    // fill the packet with 16 occurrences of the MAC
-    // starting at the 7th byte so that the first 6
-    // bytes stay as 0xFF
-    for i in 1..17 {
-        for j in 0..6 {
-            packet[i * 6 + j] = mac[j];
+    if let Some(password) = password {
+        packet.extend(parse_password(password)?);
+    }
+
+    Ok(packet)
+}
+
+/// Creates a magic packet byte array for the given IEEE EUI-64 address
+///
+/// EUI-64 addresses are 8 bytes long, used by e.g. InfiniBand and some
+/// modern NICs. Accepted formats are the following:
+///
+/// aa-bb-cc-dd-ee-ff-00-11
+///
+/// aa:bb:cc:dd:ee:ff:00:11
+///
+/// aabbccddeeff0011
+pub fn create_magic_packet_eui64(mac: &str) -> Result<[u8; 134], ParseError> {
+    let mut packet = [0xFFu8; 134];
+
+    // parse EUI-64 address
+    let mac = parse_eui64(mac)?;
+
+    // fill the packet with 16 occurrences of the address
+    // starting right after the 6-byte 0xFF header
+    for rep in 0..16 {
+        for j in 0..EUI64_LEN {
+            packet[6 + rep * EUI64_LEN + j] = mac[j];
         }
     }
 
     Ok(packet)
 }
 
+/// Default broadcast address and port Wake-on-LAN magic packets are sent to
+const WOL_BROADCAST_ADDR: &str = "255.255.255.255:9";
+
+/// Error returned when building or sending a magic packet fails
+#[derive(Debug)]
+pub enum SendError {
+    /// The MAC address could not be parsed
+    Parse(ParseError),
+    /// Sending the packet over the network failed
+    Io(io::Error),
+}
+
+impl Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SendError::Parse(ref e) => write!(f, "{}", e),
+            SendError::Io(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for SendError {
+    fn description(&self) -> &str {
+        "failed to send magic packet"
+    }
+}
+
+impl From<ParseError> for SendError {
+    fn from(e: ParseError) -> SendError {
+        SendError::Parse(e)
+    }
+}
+
+impl From<io::Error> for SendError {
+    fn from(e: io::Error) -> SendError {
+        SendError::Io(e)
+    }
+}
+
+/// A constructed Wake-on-LAN magic packet, ready to be sent over the network
+pub struct WolPacket {
+    packet: [u8; 102],
+}
+
+impl WolPacket {
+    /// Parses `mac` and builds the magic packet for it
+    pub fn new(mac: &str) -> Result<WolPacket, ParseError> {
+        let packet = create_magic_packet(mac)?;
+        Ok(WolPacket { packet })
+    }
+
+    /// Returns the raw bytes of the magic packet
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.packet
+    }
+
+    /// Sends the magic packet to the default broadcast address (255.255.255.255:9)
+    pub fn send(&self) -> io::Result<()> {
+        self.send_to(WOL_BROADCAST_ADDR)
+    }
+
+    /// Sends the magic packet to `addr`, e.g. a subnet broadcast address or a relay
+    pub fn send_to<A: ToSocketAddrs>(&self, addr: A) -> io::Result<()> {
+        let socket = UdpSocket::bind("0.0.0.0:0")?;
+        socket.set_broadcast(true)?;
+        socket.send_to(&self.packet, addr)?;
+        Ok(())
+    }
+}
+
+/// Parses `mac` and broadcasts its magic packet to 255.255.255.255:9
+pub fn send_magic(mac: &str) -> Result<(), SendError> {
+    send_magic_to(mac, WOL_BROADCAST_ADDR)
+}
+
+/// Parses `mac` and sends its magic packet to `addr`
+///
+/// Use this to target a specific subnet broadcast address (e.g.
+/// `192.168.1.255:9`) or a forwarding relay instead of the global
+/// broadcast address.
+pub fn send_magic_to<A: ToSocketAddrs>(mac: &str, addr: A) -> Result<(), SendError> {
+    let packet = WolPacket::new(mac)?;
+    packet.send_to(addr)?;
+    Ok(())
+}
+
 #[test]
 fn test_valid_ok() {
     assert!(create_magic_packet("ff:aa:bb:cc:dd:ee").is_ok());
@@ -155,3 +504,124 @@ fn test_mac() {
 
     assert!(parse_mac("aa:aabbccddeeffaa").is_err(), "bad format");
 }
+
+#[test]
+fn test_password() {
+    // no password, same as the plain packet
+    let pkt = create_magic_packet_with_password("aa:bb:cc:dd:ee:ff", None).unwrap();
+    assert_eq!(pkt.len(), 102);
+
+    // 6-byte password
+    let pkt =
+        create_magic_packet_with_password("aa:bb:cc:dd:ee:ff", Some("11:22:33:44:55:66"))
+            .unwrap();
+    assert_eq!(pkt.len(), 108);
+    assert_eq!(&pkt[102..108], &[0x11, 0x22, 0x33, 0x44, 0x55, 0x66]);
+
+    // 4-byte password
+    let pkt = create_magic_packet_with_password("aa:bb:cc:dd:ee:ff", Some("11:22:33:44"))
+        .unwrap();
+    assert_eq!(pkt.len(), 106);
+    assert_eq!(&pkt[102..106], &[0x11, 0x22, 0x33, 0x44]);
+
+    // bad password length
+    assert!(create_magic_packet_with_password("aa:bb:cc:dd:ee:ff", Some("11:22:33")).is_err());
+}
+
+#[test]
+fn test_mac_addr_from_str_and_display() {
+    let mac: MacAddr = "AA:bb:CC:dd:EE:ff".parse().unwrap();
+    assert_eq!(mac.octets(), [0xAA, 0xBB, 0xCC, 0xDD, 0xEE, 0xFF]);
+    assert_eq!(mac.to_string(), "aa:bb:cc:dd:ee:ff");
+
+    assert!("not a mac".parse::<MacAddr>().is_err());
+}
+
+#[test]
+fn test_mac_addr_equality_and_hash() {
+    use std::collections::HashSet;
+
+    let a: MacAddr = "aa:bb:cc:dd:ee:ff".parse().unwrap();
+    let b: MacAddr = "aa-bb-cc-dd-ee-ff".parse().unwrap();
+    assert_eq!(a, b);
+
+    let mut set = HashSet::new();
+    set.insert(a);
+    assert!(set.contains(&b));
+}
+
+#[test]
+fn test_strict_valid_ok() {
+    assert!(MacAddr::parse_strict("aa:bb:cc:dd:ee:ff").is_ok());
+    assert!(MacAddr::parse_strict("aa-bb-cc-dd-ee-ff").is_ok());
+    assert!(MacAddr::parse_strict("aabb.ccdd.eeff").is_ok());
+    assert!(MacAddr::parse_strict("aabbccddeeff").is_ok());
+}
+
+#[test]
+fn test_strict_rejects_mixed_separators() {
+    assert!(MacAddr::parse_strict("ca.11:ab-1e.ba:be").is_err());
+    assert!(MacAddr::parse_strict("ca11ab1eba:be").is_err());
+}
+
+#[test]
+fn test_strict_rejects_wrong_group_width() {
+    // 1-char groups instead of 2
+    assert!(MacAddr::parse_strict("a:a:b:b:c:c:d:d:e:e:f:f").is_err());
+    // 3-char groups instead of 4
+    assert!(MacAddr::parse_strict("aab.bcc.dde.eff").is_err());
+}
+
+#[test]
+fn test_strict_matches_lenient_bytes() {
+    let strict = MacAddr::parse_strict("aa:bb:cc:dd:ee:ff").unwrap();
+    let lenient: MacAddr = "aa:bb:cc:dd:ee:ff".parse().unwrap();
+    assert_eq!(strict, lenient);
+}
+
+#[test]
+fn test_eui64_valid_ok() {
+    assert!(create_magic_packet_eui64("ff:aa:bb:cc:dd:ee:00:11").is_ok());
+    assert!(create_magic_packet_eui64("de-ad-be-ef-ba-be-00-11").is_ok());
+    assert!(create_magic_packet_eui64("ca11ab1ebabe0011").is_ok());
+}
+
+#[test]
+fn test_eui64_invalid_err() {
+    // too short for EUI-64, but a valid EUI-48 length
+    assert!(create_magic_packet_eui64("ff:aa:bb:cc:dd:ee").is_err());
+    // too long
+    assert!(create_magic_packet_eui64("ff:aa:bb:cc:dd:ee:00:11:22").is_err());
+}
+
+#[test]
+fn test_eui64_magic() {
+    let pkt = create_magic_packet_eui64("aa:aa:aa:aa:aa:aa:aa:aa").unwrap();
+
+    assert_eq!(pkt.len(), 134);
+
+    // starts with padding
+    let cmp = [255, 255, 255, 255, 255, 255];
+    assert_eq!(&pkt[..6], &cmp);
+
+    // follows with address
+    let cmp = [170u8; 8];
+    assert_eq!(&pkt[6..14], &cmp);
+
+    // ends with address
+    assert_eq!(&pkt[134 - 8..134], &cmp);
+}
+
+#[test]
+fn test_send_to() {
+    let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let packet = WolPacket::new("aa:bb:cc:dd:ee:ff").unwrap();
+    packet.send_to(addr).unwrap();
+
+    let mut buf = [0u8; 102];
+    let (len, _) = listener.recv_from(&mut buf).unwrap();
+    assert_eq!(len, 102);
+    assert_eq!(&buf[..], packet.as_bytes());
+}